@@ -0,0 +1,213 @@
+use std::env;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use tokio::fs;
+use tokio_util::io::StreamReader;
+use futures_util::TryStreamExt;
+use flate2::read::GzDecoder;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use axum::{
+    headers::{authorization::Bearer, Authorization},
+    extract::{BodyStream, Query, TypedHeader},
+    response::{Response, IntoResponse},
+    http::StatusCode,
+    Json,
+};
+
+use crate::models::{DeployQuery, DeployResponse, DeployedFile};
+
+const UPLOADS_DIR: &str = "./uploads";
+
+
+fn generate_temp_name() -> String {
+    let mut rng = thread_rng();
+
+    (0..10)
+        .map(|_| rng.sample(Alphanumeric) as char)
+        .collect::<String>()
+}
+
+
+/// resolves `./uploads/<directory>`, rejecting `..`/absolute segments in
+/// `directory` up front and, once the directory exists, canonicalizing it
+/// to confirm it still lands under the uploads root (mirrors
+/// `listing::resolve_dir`) — this is the extraction root itself, not just
+/// the entries extracted into it, so it must be validated the same way
+async fn resolve_target_dir(directory: &str) -> Result<(PathBuf, String), Response> {
+    let trimmed = directory.trim_matches('/').to_string();
+    let relative = PathBuf::from(&trimmed);
+
+    if relative.components().any(|component| !matches!(component, Component::Normal(_))) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "`directory` must be a plain relative path with no `..` segments",
+        ).into_response())
+    }
+
+    let target_dir = PathBuf::from(UPLOADS_DIR).join(&relative);
+
+    if let Err(err) = fs::create_dir_all(&target_dir).await {
+        if err.kind() != io::ErrorKind::AlreadyExists {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Creating the target directory failed",
+            ).into_response())
+        }
+    }
+
+    let canonical_root = fs::canonicalize(UPLOADS_DIR).await.map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to resolve the uploads directory",
+    ).into_response())?;
+
+    let canonical_target = fs::canonicalize(&target_dir).await.map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to resolve the target directory",
+    ).into_response())?;
+
+    if !canonical_target.starts_with(&canonical_root) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "`directory` escapes the uploads root",
+        ).into_response())
+    }
+
+    Ok((canonical_target, trimmed))
+}
+
+
+async fn stream_body_to_file(body: BodyStream, path: &PathBuf) -> Result<(), Response> {
+    let mut reader = StreamReader::new(
+        body.map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    );
+    let mut file = fs::File::create(path).await.map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Writing to file system failed",
+    ).into_response())?;
+
+    tokio::io::copy(&mut reader, &mut file).await.map_err(|_| (
+        StatusCode::BAD_REQUEST,
+        "Improper bytes sent",
+    ).into_response())?;
+
+    Ok(())
+}
+
+
+/// extracts a gzipped tar archive into `target_dir`, rejecting any entry
+/// whose normalized path would escape it, and returns the CDN-relative
+/// path/URL of each file written (matching `UploadResponse`/`ListingEntry`,
+/// rather than leaking the on-disk `./uploads` storage path)
+fn extract_tar_gz(archive_path: &Path, target_dir: &Path, directory: &str) -> Result<Vec<DeployedFile>, String> {
+    let file = std::fs::File::open(archive_path).map_err(|err| err.to_string())?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+
+    let mut extracted = Vec::new();
+
+    for entry in archive.entries().map_err(|err| err.to_string())? {
+        let mut entry = entry.map_err(|err| err.to_string())?;
+        let entry_path = entry.path().map_err(|err| err.to_string())?.into_owned();
+
+        // symlink/hardlink entries let a later, innocent-looking entry resolve
+        // through them at unpack time and escape `target_dir` even though its
+        // own path contains no `..` — only regular files and directories are
+        // safe to unpack here
+        let entry_type = entry.header().entry_type();
+        if entry_type != tar::EntryType::Regular && entry_type != tar::EntryType::Directory {
+            return Err(format!("entry `{}` has disallowed type {:?}", entry_path.display(), entry_type))
+        }
+
+        if entry_path.components().any(|component| component == Component::ParentDir) {
+            return Err(format!("entry `{}` escapes the uploads root", entry_path.display()))
+        }
+
+        let destination = target_dir.join(&entry_path);
+        if !destination.starts_with(target_dir) {
+            return Err(format!("entry `{}` escapes the uploads root", entry_path.display()))
+        }
+
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+
+        entry.unpack(&destination).map_err(|err| err.to_string())?;
+
+        if entry_type != tar::EntryType::Regular {
+            continue
+        }
+
+        let path = if directory.is_empty() {
+            format!("/{}", entry_path.display())
+        } else {
+            format!("/{}/{}", directory, entry_path.display())
+        };
+        extracted.push(DeployedFile {
+            full_url: format!("{}{}", crate::CDN_URL, path),
+            path,
+        });
+    }
+
+    Ok(extracted)
+}
+
+
+/// handler for POST /deploy: accepts a gzipped tar stream in the request
+/// body and extracts it under `./uploads/<directory>`, for pushing a
+/// whole static site or asset bundle in one request
+pub async fn post_deploy(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Query(query): Query<DeployQuery>,
+    body: BodyStream,
+) -> Response {
+    let Ok(auth_token) = env::var("auth") else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to get auth token from env",
+        ).into_response()
+    };
+
+    if auth.token() != auth_token {
+        return (
+            StatusCode::UNAUTHORIZED,
+            "Incorrect authorization token",
+        ).into_response()
+    }
+
+    let (target_dir, directory) = match resolve_target_dir(&query.directory.unwrap_or_default()).await {
+        Ok(resolved) => resolved,
+        Err(response) => return response,
+    };
+
+    let temp_path = PathBuf::from(format!("{}/.deploy-{}.tar.gz", UPLOADS_DIR, generate_temp_name()));
+
+    if let Err(response) = stream_body_to_file(body, &temp_path).await {
+        let _ = fs::remove_file(&temp_path).await;
+        return response
+    }
+
+    let extraction = {
+        let archive_path = temp_path.clone();
+        let target_dir = target_dir.clone();
+        tokio::task::spawn_blocking(move || extract_tar_gz(&archive_path, &target_dir, &directory)).await
+    };
+
+    let _ = fs::remove_file(&temp_path).await;
+
+    match extraction {
+        Ok(Ok(extracted)) => (
+            StatusCode::OK,
+            Json(DeployResponse { extracted }),
+        ).into_response(),
+        Ok(Err(message)) => (
+            StatusCode::BAD_REQUEST,
+            message,
+        ).into_response(),
+        Err(_) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Extracting the archive failed",
+        ).into_response(),
+    }
+}
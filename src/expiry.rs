@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::fs;
+
+use axum::{
+    response::{Response, IntoResponse},
+    http::StatusCode,
+};
+
+use crate::models::UploadMetadata;
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".meta.json");
+    PathBuf::from(sidecar)
+}
+
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+
+/// parses a rustypaste-style duration like `1h`, `30m`, `10s` or `2d`
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let input = input.trim();
+    if input.len() < 2 {
+        return None
+    }
+
+    let (amount, suffix) = input.split_at(input.len() - 1);
+    let amount: u64 = amount.parse().ok()?;
+
+    let seconds = match suffix {
+        "s" => Some(amount),
+        "m" => amount.checked_mul(60),
+        "h" => amount.checked_mul(60 * 60),
+        "d" => amount.checked_mul(60 * 60 * 24),
+        _ => return None,
+    }?;
+
+    Some(Duration::from_secs(seconds))
+}
+
+
+/// writes a sidecar metadata file describing when `path` expires and/or
+/// whether it should be deleted after its first successful GET; a no-op
+/// when neither `expires` nor `oneshot` were requested
+pub async fn write_sidecar(path: &Path, expires: Option<Duration>, oneshot: bool) {
+    if expires.is_none() && !oneshot {
+        return
+    }
+
+    let metadata = UploadMetadata {
+        expires_at: expires.map(|duration| now_secs().saturating_add(duration.as_secs())),
+        oneshot,
+    };
+
+    if let Ok(json) = serde_json::to_vec(&metadata) {
+        let _ = fs::write(sidecar_path(path), json).await;
+    }
+}
+
+
+/// whether `path` already has sidecar metadata written for it
+pub async fn has_sidecar(path: &Path) -> bool {
+    fs::metadata(sidecar_path(path)).await.is_ok()
+}
+
+
+async fn read_sidecar(path: &Path) -> Option<UploadMetadata> {
+    let bytes = fs::read(sidecar_path(path)).await.ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+
+async fn delete_with_sidecar(path: &Path) {
+    let _ = fs::remove_file(path).await;
+    let _ = fs::remove_file(sidecar_path(path)).await;
+}
+
+
+/// checks a served file's sidecar metadata: expired files are deleted and
+/// rejected with 404. for one-shot files, this atomically claims the file
+/// by removing its sidecar — unlink is the single point of synchronization,
+/// so of two concurrent GETs only one can win the claim and go on to serve
+/// + delete the file; the loser observes it as already gone (404)
+pub async fn check(path: &Path) -> Result<bool, Response> {
+    let Some(metadata) = read_sidecar(path).await else {
+        return Ok(false)
+    };
+
+    if let Some(expires_at) = metadata.expires_at {
+        if now_secs() >= expires_at {
+            delete_with_sidecar(path).await;
+
+            return Err((
+                StatusCode::NOT_FOUND,
+                "The requested file was not found on the CDN",
+            ).into_response())
+        }
+    }
+
+    if !metadata.oneshot {
+        return Ok(false)
+    }
+
+    match fs::remove_file(sidecar_path(path)).await {
+        Ok(()) => Ok(true),
+        Err(_) => Err((
+            StatusCode::NOT_FOUND,
+            "The requested file was not found on the CDN",
+        ).into_response()),
+    }
+}
+
+
+/// deletes a one-shot file once it has actually been served successfully;
+/// its sidecar was already removed as part of the atomic claim in `check`
+pub async fn consume(path: &Path) {
+    let _ = fs::remove_file(path).await;
+}
+
+
+/// periodically scans `./uploads` for expired files and removes them, so
+/// expiring uploads don't need manual cleanup
+pub async fn cleanup_task() {
+    let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        scan_dir(Path::new("./uploads")).await;
+    }
+}
+
+
+fn scan_dir(dir: &Path) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + '_>> {
+    Box::pin(async move {
+        let mut entries = match fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+
+            let file_type = match entry.file_type().await {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+
+            if file_type.is_dir() {
+                scan_dir(&path).await;
+                continue
+            }
+
+            if !path.to_string_lossy().ends_with(".meta.json") {
+                continue
+            }
+
+            let Some(metadata) = read_sidecar_file(&path).await else {
+                continue
+            };
+
+            if let Some(expires_at) = metadata.expires_at {
+                if now_secs() >= expires_at {
+                    let data_path = path.to_string_lossy()
+                        .trim_end_matches(".meta.json")
+                        .to_string();
+
+                    let _ = fs::remove_file(data_path).await;
+                    let _ = fs::remove_file(&path).await;
+                }
+            }
+        }
+    })
+}
+
+
+async fn read_sidecar_file(sidecar_path: &Path) -> Option<UploadMetadata> {
+    let bytes = fs::read(sidecar_path).await.ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
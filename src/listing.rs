@@ -0,0 +1,171 @@
+use std::env;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use tokio::fs;
+
+use axum::{
+    headers::{authorization::Bearer, Authorization},
+    extract::{Path, TypedHeader},
+    response::{Response, IntoResponse, Html},
+    http::{HeaderMap, StatusCode, header},
+    Json,
+};
+
+use crate::models::ListingEntry;
+use crate::CDN_URL;
+
+const UPLOADS_DIR: &str = "./uploads";
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "webp", "gif", "bmp"];
+
+
+/// resolves `./uploads/<path>`, rejecting anything that canonicalizes
+/// outside of the uploads root
+async fn resolve_dir(path: &str) -> Result<PathBuf, Response> {
+    let dir = PathBuf::from(format!("{}/{}", UPLOADS_DIR, path.trim_matches('/')));
+
+    let canonical_root = fs::canonicalize(UPLOADS_DIR).await.map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to resolve the uploads directory",
+    ).into_response())?;
+
+    let canonical_dir = fs::canonicalize(&dir).await.map_err(|_| (
+        StatusCode::NOT_FOUND,
+        "The requested directory was not found on the CDN",
+    ).into_response())?;
+
+    if !canonical_dir.starts_with(&canonical_root) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Path escapes the uploads root",
+        ).into_response())
+    }
+
+    Ok(dir)
+}
+
+
+async fn list_entries(dir: &PathBuf, url_prefix: &str) -> Result<Vec<ListingEntry>, Response> {
+    let mut read_dir = fs::read_dir(dir).await.map_err(|_| (
+        StatusCode::NOT_FOUND,
+        "The requested directory was not found on the CDN",
+    ).into_response())?;
+
+    let mut entries = Vec::new();
+
+    while let Ok(Some(entry)) = read_dir.next_entry().await {
+        let Ok(metadata) = entry.metadata().await else {
+            continue
+        };
+
+        if metadata.is_dir() {
+            continue
+        }
+
+        let filename = entry.file_name().to_string_lossy().to_string();
+        if filename.ends_with(".meta.json") {
+            continue
+        }
+
+        let path = format!("/{}/{}", url_prefix.trim_matches('/'), filename);
+        let modified = metadata.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        entries.push(ListingEntry {
+            full_url: format!("{}{}", CDN_URL, path),
+            filename,
+            path,
+            size: metadata.len(),
+            modified,
+        });
+    }
+
+    Ok(entries)
+}
+
+
+fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+
+fn render_gallery(entries: &[ListingEntry]) -> String {
+    let items: String = entries.iter().map(|entry| {
+        let is_image = IMAGE_EXTENSIONS.iter()
+            .any(|ext| entry.filename.to_lowercase().ends_with(ext));
+
+        let url = escape_html(&entry.full_url);
+        let name = escape_html(&entry.filename);
+
+        if is_image {
+            format!(
+                "<figure><a href=\"{url}\"><img src=\"{url}?w=300\" loading=\"lazy\" alt=\"{name}\"></a><figcaption>{name}</figcaption></figure>",
+                url = url,
+                name = name,
+            )
+        } else {
+            format!(
+                "<figure><a href=\"{url}\">{name}</a></figure>",
+                url = url,
+                name = name,
+            )
+        }
+    }).collect();
+
+    format!(
+        "<!DOCTYPE html><html><head><title>bomb-cdn gallery</title></head><body><div class=\"gallery\">{}</div></body></html>",
+        items,
+    )
+}
+
+
+/// handler for GET /list/*path: lists the files under `./uploads/<path>`,
+/// as JSON by default or as an HTML thumbnail gallery when the client
+/// sends `Accept: text/html`
+pub async fn get_list(
+    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Ok(auth_token) = env::var("auth") else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to get auth token from env",
+        ).into_response()
+    };
+
+    if auth.token() != auth_token {
+        return (
+            StatusCode::UNAUTHORIZED,
+            "Incorrect authorization token",
+        ).into_response()
+    }
+
+    let dir = match resolve_dir(&path).await {
+        Ok(dir) => dir,
+        Err(response) => return response,
+    };
+
+    let entries = match list_entries(&dir, &path).await {
+        Ok(entries) => entries,
+        Err(response) => return response,
+    };
+
+    let wants_html = headers.get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.contains("text/html"))
+        .unwrap_or(false);
+
+    if wants_html {
+        Html(render_gallery(&entries)).into_response()
+    } else {
+        Json(entries).into_response()
+    }
+}
@@ -1,6 +1,10 @@
 
 use dotenv::dotenv;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::StreamReader;
+use futures_util::TryStreamExt;
+use sha2::{Sha256, Digest};
 use std::{
     io::ErrorKind::{AlreadyExists, NotFound},
     collections::HashMap,
@@ -9,13 +13,12 @@ use std::{
     env,
 };
 
-use tower_http::services::{ServeDir, ServeFile};
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
 use axum::{
     headers::{authorization::Bearer, Authorization},
-    routing::{get, post, delete, get_service},
+    routing::{get, post, delete},
     extract::{Path, Query, Multipart, TypedHeader},
     response::{Response, IntoResponse},
     http::StatusCode,
@@ -26,8 +29,13 @@ use axum::{
 };
 
 mod models;
+mod transform;
+mod expiry;
+mod deploy;
+mod listing;
+mod mime_check;
 
-const CDN_URL: &str = "https://cdn.tomthebomb.dev";
+pub(crate) const CDN_URL: &str = "https://cdn.tomthebomb.dev";
 const MAX_FILE_SIZE: usize = 30_000_000;
 const PORT: u16 = 8030;
 
@@ -43,6 +51,78 @@ fn generate_filename() -> String {
 }
 
 
+/// streams a multipart field to disk, rejecting (and cleaning up the partial
+/// file) once more than `MAX_FILE_SIZE` bytes have been received, so the
+/// whole upload never has to sit in memory at once. returns the hex-encoded
+/// SHA-256 digest of the bytes written, for content-addressed storage
+async fn stream_field_to_file(
+    field: axum::extract::multipart::Field<'_>,
+    path: &PathBuf,
+) -> Result<String, Response> {
+    let mut reader = StreamReader::new(
+        field.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))
+    );
+    let mut file = fs::File::create(path).await.map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Writing to file system failed",
+    ).into_response())?;
+
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 8192];
+    let mut written: usize = 0;
+
+    loop {
+        let read = tokio::io::AsyncReadExt::read(&mut reader, &mut buf)
+            .await
+            .map_err(|_| (
+                StatusCode::BAD_REQUEST,
+                "Improper bytes sent",
+            ).into_response())?;
+
+        if read == 0 {
+            break;
+        }
+
+        written += read;
+        if written > MAX_FILE_SIZE {
+            drop(file);
+            let _ = fs::remove_file(path).await;
+
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("uploaded files cannot exceed the limit of {} bytes", MAX_FILE_SIZE),
+            ).into_response())
+        }
+
+        if let Err(_) = file.write_all(&buf[..read]).await {
+            drop(file);
+            let _ = fs::remove_file(path).await;
+
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Writing to file system failed",
+            ).into_response())
+        }
+
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+
+/// whether uploads in this request should be stored under their SHA-256
+/// digest instead of a random/given filename, so identical uploads
+/// collapse to a single file on disk
+fn wants_content_addressed(query: &models::DirectoryQuery) -> bool {
+    query.content_addressed.unwrap_or_else(|| {
+        env::var("content_addressed")
+            .map(|value| value == "true")
+            .unwrap_or(false)
+    })
+}
+
+
 async fn post_upload(
     TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
     Query(query): Query<models::DirectoryQuery>,
@@ -57,6 +137,15 @@ async fn post_upload(
                 "Incorrect authorization token",
             ).into_response()
         } else {
+            let expires = match query.expires.as_deref().map(expiry::parse_duration) {
+                Some(Some(duration)) => Some(duration),
+                Some(None) => return (
+                    StatusCode::BAD_REQUEST,
+                    "Invalid `expires` duration, expected e.g. `30m`, `1h` or `2d`",
+                ).into_response(),
+                None => None,
+            };
+
             if let Ok(Some(field)) = multipart.next_field().await {
                 let filename = field.file_name()
                     .map(|s| s.to_string())
@@ -81,25 +170,53 @@ async fn post_upload(
                     }
                 }
 
-                if let Ok(bytes) = field.bytes().await {
-                    if bytes.len() > MAX_FILE_SIZE {
-                        return (
-                            StatusCode::PAYLOAD_TOO_LARGE,
-                            format!("uploaded files cannot exceed the limit of {} bytes", MAX_FILE_SIZE),
-                        ).into_response()
-                    }
+                let digest = match stream_field_to_file(field, &path).await {
+                    Ok(digest) => digest,
+                    Err(response) => return response,
+                };
 
-                    if let Err(_) = fs::write(&path, bytes).await {
+                let declared_extension = PathBuf::from(&filename)
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_string());
+
+                if let Err(response) = mime_check::validate(&path, declared_extension.as_deref()).await {
+                    let _ = fs::remove_file(&path).await;
+                    return response
+                }
+
+                let (path, filename, is_dedup_hit) = if wants_content_addressed(&query) {
+                    let extension = PathBuf::from(&filename)
+                        .extension()
+                        .map(|ext| format!(".{}", ext.to_string_lossy()))
+                        .unwrap_or_default();
+                    let hashed_filename = format!("{}{}", digest, extension);
+                    let hashed_path = path.with_file_name(&hashed_filename);
+
+                    if hashed_path == path {
+                        (path, filename, false)
+                    } else if fs::metadata(&hashed_path).await.is_ok() {
+                        // an identical file is already stored, drop the upload we just wrote
+                        let _ = fs::remove_file(&path).await;
+                        (hashed_path, hashed_filename, true)
+                    } else if fs::rename(&path, &hashed_path).await.is_ok() {
+                        (hashed_path, hashed_filename, false)
+                    } else {
                         return (
                             StatusCode::INTERNAL_SERVER_ERROR,
                             "Writing to file system failed",
                         ).into_response()
                     }
                 } else {
-                    return (
-                        StatusCode::BAD_REQUEST,
-                        "Improper bytes sent",
-                    ).into_response()
+                    (path, filename, false)
+                };
+
+                // on a dedup hit, the physical file is shared with whoever
+                // uploaded it first — first-writer-wins, so this request's
+                // `expires`/`oneshot` must not clobber metadata that's
+                // already there (e.g. a later `oneshot=true` upload would
+                // otherwise make an earlier "permanent" upload self-delete)
+                if !is_dedup_hit {
+                    expiry::write_sidecar(&path, expires, query.oneshot.unwrap_or(false)).await;
                 }
 
                 let path_string = path.display()
@@ -212,25 +329,15 @@ async fn main() {
             }
         });
 
+    tokio::spawn(expiry::cleanup_task());
+
     let app: Router<Body> = Router::new()
         .route("/", get(get_root))
         .route("/upload", post(post_upload))
+        .route("/deploy", post(deploy::post_deploy))
         .route("/delete/*path", delete(delete_file))
-        .fallback(
-            get_service(
-                ServeDir::new("./uploads")
-                    .fallback(
-                        ServeDir::new("./static/")
-                        .fallback(ServeFile::new("./static/notfound.html"))
-                    )
-            )
-            .handle_error(|err| async move {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to serve CDN files: {}", err),
-                )
-            })
-        );
+        .route("/list/*path", get(listing::get_list))
+        .route("/*path", get(transform::serve_or_transform));
 
     run(app, PORT).await;
 }
\ No newline at end of file
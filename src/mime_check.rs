@@ -0,0 +1,101 @@
+use std::env;
+use std::path::Path;
+
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+
+use axum::{
+    response::{Response, IntoResponse},
+    http::StatusCode,
+};
+
+/// `infer` only ever needs a small header to sniff a type, so reading more
+/// than this into memory would just be wasted work on large uploads
+const SNIFF_BYTES: usize = 8192;
+
+
+/// parses the `allowed_types` env var (e.g. `image/*,video/mp4,application/pdf`);
+/// `None` means no allowlist is configured, so no content type is rejected
+/// by it (a declared extension that mismatches the sniffed type still is)
+fn allowed_types() -> Option<Vec<String>> {
+    env::var("allowed_types").ok().map(|value| {
+        value.split(',')
+            .map(|entry| entry.trim().to_lowercase())
+            .filter(|entry| !entry.is_empty())
+            .collect()
+    })
+}
+
+
+fn is_allowed(mime: &str, allowlist: &[String]) -> bool {
+    allowlist.iter().any(|pattern| {
+        match pattern.strip_suffix("/*") {
+            Some(prefix) => mime.split('/').next() == Some(prefix),
+            None => pattern == mime,
+        }
+    })
+}
+
+
+fn normalize_extension(extension: &str) -> String {
+    match extension.to_lowercase().as_str() {
+        "jpg" => "jpeg".to_string(),
+        other => other.to_string(),
+    }
+}
+
+
+/// sniffs the real content type of a just-written upload from its leading
+/// bytes (magic-byte detection via `infer`) and enforces the configured
+/// `allowed_types` allowlist, rejecting a mismatch between the declared
+/// extension and the sniffed type
+pub async fn validate(path: &Path, declared_extension: Option<&str>) -> Result<(), Response> {
+    let mut file = fs::File::open(path).await.map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to read the uploaded file for validation",
+    ).into_response())?;
+
+    let mut bytes = vec![0u8; SNIFF_BYTES];
+    let read = file.read(&mut bytes).await.map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to read the uploaded file for validation",
+    ).into_response())?;
+    bytes.truncate(read);
+
+    let allowlist = allowed_types();
+
+    let Some(kind) = infer::get(&bytes) else {
+        return match allowlist {
+            Some(_) => Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Could not determine the content type of the uploaded file",
+            ).into_response()),
+            None => Ok(()),
+        }
+    };
+
+    let mime = kind.mime_type();
+
+    if let Some(allowlist) = allowlist {
+        if !is_allowed(mime, &allowlist) {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("uploads of type `{}` are not allowed", mime),
+            ).into_response())
+        }
+    }
+
+    if let Some(extension) = declared_extension {
+        if normalize_extension(extension) != normalize_extension(kind.extension()) {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!(
+                    "declared extension `.{}` does not match the sniffed content type `{}`",
+                    extension, mime,
+                ),
+            ).into_response())
+        }
+    }
+
+    Ok(())
+}
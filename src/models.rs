@@ -10,4 +10,48 @@ pub struct UploadResponse {
 #[derive(Deserialize)]
 pub struct DirectoryQuery {
     pub directory: Option<String>,
+    pub content_addressed: Option<bool>,
+    pub expires: Option<String>,
+    pub oneshot: Option<bool>,
+}
+
+/// sidecar metadata stored next to an upload when it was given an
+/// `?expires=` duration and/or `?oneshot=true` on upload
+#[derive(Serialize, Deserialize)]
+pub struct UploadMetadata {
+    pub expires_at: Option<u64>,
+    pub oneshot: bool,
+}
+
+#[derive(Deserialize)]
+pub struct DeployQuery {
+    pub directory: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DeployedFile {
+    pub path: String,
+    pub full_url: String,
+}
+
+#[derive(Serialize)]
+pub struct DeployResponse {
+    pub extracted: Vec<DeployedFile>,
+}
+
+#[derive(Serialize)]
+pub struct ListingEntry {
+    pub filename: String,
+    pub path: String,
+    pub full_url: String,
+    pub size: u64,
+    pub modified: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ImageTransformQuery {
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+    pub format: Option<String>,
+    pub quality: Option<u8>,
 }
\ No newline at end of file
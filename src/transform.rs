@@ -0,0 +1,240 @@
+use std::path::PathBuf;
+
+use tokio::fs;
+use tower::ServiceExt;
+use tower_http::services::{ServeDir, ServeFile};
+use sha2::{Sha256, Digest};
+use image::imageops::FilterType;
+
+use axum::{
+    extract::{Path, Query},
+    response::{Response, IntoResponse},
+    http::{Request, StatusCode, header},
+    body::Body,
+};
+
+use crate::models::ImageTransformQuery;
+
+const UPLOADS_DIR: &str = "./uploads";
+const CACHE_DIR: &str = "./uploads/.cache";
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "webp", "gif", "bmp"];
+
+// `/*path` is unauthenticated, so `w`/`h`/`quality` are attacker-controlled;
+// without a cap, `resize_exact`/`resize` can be asked to allocate an
+// arbitrarily large output buffer, which either panics with a capacity
+// overflow or triggers an allocator abort() that takes down the process
+const MAX_DIMENSION: u32 = 4096;
+const MIN_JPEG_QUALITY: u8 = 1;
+const MAX_JPEG_QUALITY: u8 = 100;
+
+
+/// resolves `./uploads/<path>` and canonicalizes it, returning `None` if
+/// the file doesn't exist or canonicalizes outside of the uploads root
+/// (e.g. a `../../etc/passwd`-style request) — mirrors `listing::resolve_dir`
+async fn resolve_within_uploads(path: &PathBuf) -> Option<PathBuf> {
+    let canonical_root = fs::canonicalize(UPLOADS_DIR).await.ok()?;
+    let canonical_path = fs::canonicalize(path).await.ok()?;
+
+    if canonical_path.starts_with(&canonical_root) {
+        Some(canonical_path)
+    } else {
+        None
+    }
+}
+
+
+fn is_image_path(path: &PathBuf) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+
+fn content_type_for(format: &str) -> &'static str {
+    match format {
+        "jpeg" | "jpg" => "image/jpeg",
+        "webp" => "image/webp",
+        _ => "image/png",
+    }
+}
+
+
+/// rejects `w`/`h` larger than `MAX_DIMENSION` (or zero) and `quality`
+/// outside the valid JPEG range, before any of it reaches the `image` crate
+fn validate_transform_query(query: &ImageTransformQuery) -> Result<(), Response> {
+    let dimension_in_range = |dim: u32| dim > 0 && dim <= MAX_DIMENSION;
+
+    if query.w.is_some_and(|w| !dimension_in_range(w)) || query.h.is_some_and(|h| !dimension_in_range(h)) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("`w`/`h` must be between 1 and {}", MAX_DIMENSION),
+        ).into_response())
+    }
+
+    if let Some(quality) = query.quality {
+        if quality < MIN_JPEG_QUALITY || quality > MAX_JPEG_QUALITY {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("`quality` must be between {} and {}", MIN_JPEG_QUALITY, MAX_JPEG_QUALITY),
+            ).into_response())
+        }
+    }
+
+    Ok(())
+}
+
+
+fn cache_filename(url_path: &str, query: &ImageTransformQuery, format: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(url_path.as_bytes());
+    let src_hash = format!("{:x}", hasher.finalize());
+
+    let params = format!(
+        "w={}-h={}-q={}",
+        query.w.map(|w| w.to_string()).unwrap_or_default(),
+        query.h.map(|h| h.to_string()).unwrap_or_default(),
+        query.quality.unwrap_or(80),
+    );
+
+    format!("{}-{}.{}", src_hash, params, format)
+}
+
+
+/// loads the image at `source_path`, resizes it per the query (preserving
+/// aspect ratio when only one of `w`/`h` is given) and re-encodes it to the
+/// requested format, caching the result under `./uploads/.cache`
+async fn transform_image(
+    source_path: &PathBuf,
+    url_path: &str,
+    query: &ImageTransformQuery,
+) -> Result<Response, Response> {
+    let format = query.format
+        .as_deref()
+        .map(|format| format.to_lowercase())
+        .or_else(|| source_path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase))
+        .unwrap_or_else(|| "png".to_string());
+
+    let cache_path = PathBuf::from(CACHE_DIR).join(cache_filename(url_path, query, &format));
+
+    if let Ok(cached) = fs::read(&cache_path).await {
+        return Ok((
+            [(header::CONTENT_TYPE, content_type_for(&format))],
+            cached,
+        ).into_response())
+    }
+
+    let source_bytes = fs::read(source_path).await.map_err(|_| (
+        StatusCode::NOT_FOUND,
+        "The requested file was not found on the CDN",
+    ).into_response())?;
+
+    let source_image = image::load_from_memory(&source_bytes).map_err(|_| (
+        StatusCode::BAD_REQUEST,
+        "Source file is not a valid image",
+    ).into_response())?;
+
+    let resized = match (query.w, query.h) {
+        (Some(w), Some(h)) => source_image.resize_exact(w, h, FilterType::Lanczos3),
+        (Some(w), None) => source_image.resize(w, u32::MAX, FilterType::Lanczos3),
+        (None, Some(h)) => source_image.resize(u32::MAX, h, FilterType::Lanczos3),
+        (None, None) => source_image,
+    };
+
+    let output_format = match format.as_str() {
+        "jpeg" | "jpg" => image::ImageOutputFormat::Jpeg(query.quality.unwrap_or(80)),
+        "webp" => image::ImageOutputFormat::WebP,
+        _ => image::ImageOutputFormat::Png,
+    };
+
+    let mut encoded = Vec::new();
+    resized.write_to(&mut std::io::Cursor::new(&mut encoded), output_format).map_err(|_| (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Failed to encode transformed image",
+    ).into_response())?;
+
+    if let Some(parent) = cache_path.parent() {
+        let _ = fs::create_dir_all(parent).await;
+    }
+    if let Err(err) = fs::write(&cache_path, &encoded).await {
+        eprintln!("[transform] failed to write cache entry: {}", err);
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, content_type_for(&format))],
+        encoded,
+    ).into_response())
+}
+
+
+/// serves `./uploads/*path` as-is, unless `w`/`h`/`format`/`quality` query
+/// params are present on an image, in which case it returns a resized/
+/// re-encoded variant instead (see `transform_image`)
+pub async fn serve_or_transform(
+    Path(path): Path<String>,
+    Query(query): Query<ImageTransformQuery>,
+    req: Request<Body>,
+) -> Response {
+    let wants_transform = query.w.is_some()
+        || query.h.is_some()
+        || query.format.is_some()
+        || query.quality.is_some();
+
+    let source_path = PathBuf::from(format!("{}/{}", UPLOADS_DIR, path.trim_matches('/')));
+
+    // only a path that canonicalizes to somewhere under `./uploads` is
+    // eligible for the transform/expiry fast paths below; anything else
+    // (including traversal attempts) falls through to `serve_raw`, which
+    // lets `ServeDir` apply its own traversal protection and 404 handling
+    let validated_path = resolve_within_uploads(&source_path).await;
+
+    if wants_transform {
+        if let Err(response) = validate_transform_query(&query) {
+            return response
+        }
+    }
+
+    let is_oneshot = match &validated_path {
+        Some(canonical_path) => match crate::expiry::check(canonical_path).await {
+            Ok(is_oneshot) => is_oneshot,
+            Err(response) => return response,
+        },
+        None => false,
+    };
+
+    let response = match &validated_path {
+        Some(canonical_path) if wants_transform && is_image_path(canonical_path) => {
+            match transform_image(canonical_path, &path, &query).await {
+                Ok(response) => response,
+                Err(response) => response,
+            }
+        }
+        _ => serve_raw(req).await,
+    };
+
+    if is_oneshot && response.status().is_success() {
+        if let Some(canonical_path) = &validated_path {
+            crate::expiry::consume(canonical_path).await;
+        }
+    }
+
+    response
+}
+
+
+async fn serve_raw(req: Request<Body>) -> Response {
+    let service = ServeDir::new(UPLOADS_DIR)
+        .fallback(
+            ServeDir::new("./static/")
+                .fallback(ServeFile::new("./static/notfound.html"))
+        );
+
+    match service.oneshot(req).await {
+        Ok(response) => response.map(Body::new),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to serve CDN files: {}", err),
+        ).into_response(),
+    }
+}